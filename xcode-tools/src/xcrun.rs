@@ -1,6 +1,7 @@
 use clap::Parser;
-use serde::Deserialize;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 #[derive(Parser, Debug)]
@@ -23,6 +24,10 @@ pub struct Xcrun {
     no_cache: bool,
     #[clap(short, long)]
     kill_cache: bool,
+    /// Additional root directories to scan for `Platforms/*.platform/Developer/SDKs/*.sdk`;
+    /// discovered SDKs are merged with the configured ones. See also `XCRUN_SDK_PATH`.
+    #[clap(long)]
+    sdk_search_path: Vec<String>,
     #[clap(long)]
     show_sdk_path: bool,
     #[clap(long)]
@@ -61,6 +66,378 @@ pub struct Sdk {
     pub macosx_deployment_target: String,
     /// The minimum deployment target of the SDK on iOS.
     pub ios_deployment_target: String,
+    /// Whether this SDK should be selected when `--sdk` is not given.
+    #[serde(default)]
+    pub default: bool,
+    /// Alternate toolchains (e.g. LLVM/Swift) available for this SDK,
+    /// selectable with `--toolchain`.
+    #[serde(default)]
+    pub toolchains: Vec<Toolchain>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Toolchain {
+    /// The name of the toolchain, matched against `--toolchain`.
+    pub name: String,
+    /// The path to the toolchain.
+    pub path: String,
+    /// The version of the toolchain.
+    pub version: String,
+}
+
+/// The subset of an SDK's `SDKSettings.json`/`SDKSettings.plist` that we
+/// need to populate a discovered `Sdk`.
+#[derive(Debug, Default, Deserialize)]
+struct SdkSettings {
+    #[serde(rename = "CanonicalName")]
+    canonical_name: Option<String>,
+    #[serde(rename = "Version")]
+    version: Option<String>,
+    #[serde(rename = "DefaultProperties", default)]
+    default_properties: SdkSettingsDefaultProperties,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SdkSettingsDefaultProperties {
+    #[serde(rename = "MACOSX_DEPLOYMENT_TARGET")]
+    macosx_deployment_target: Option<String>,
+    #[serde(rename = "IPHONEOS_DEPLOYMENT_TARGET")]
+    ios_deployment_target: Option<String>,
+}
+
+/// The roots to scan for `Platforms/*.platform/Developer/SDKs/*.sdk`:
+/// `--sdk-search-path` entries followed by the colon-separated
+/// `XCRUN_SDK_PATH` environment variable.
+fn discovery_roots(xcrun: &Xcrun) -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = xcrun.sdk_search_path.iter().map(PathBuf::from).collect();
+
+    if let Ok(env_roots) = std::env::var("XCRUN_SDK_PATH") {
+        roots.extend(
+            env_roots
+                .split(':')
+                .filter(|root| !root.is_empty())
+                .map(PathBuf::from),
+        );
+    }
+
+    roots
+}
+
+/// The target triple to use for SDKs found under a given `*.platform`
+/// directory name, since `SDKSettings` doesn't carry one directly.
+fn platform_target_triple(platform_name: &str) -> Option<&'static str> {
+    match platform_name {
+        "MacOSX" => Some("x86_64-apple-darwin"),
+        "iPhoneOS" => Some("aarch64-apple-ios"),
+        "iPhoneSimulator" => Some("x86_64-apple-ios-sim"),
+        "AppleTVOS" => Some("arm64-apple-tvos"),
+        "AppleTVSimulator" => Some("x86_64-apple-tvos-sim"),
+        "WatchOS" => Some("arm64-apple-watchos"),
+        "WatchSimulator" => Some("x86_64-apple-watchos-sim"),
+        _ => None,
+    }
+}
+
+/// Read `SDKSettings.json`, falling back to the legacy `SDKSettings.plist`,
+/// from inside a `*.sdk` directory.
+fn read_sdk_settings(sdk_path: &Path) -> Option<SdkSettings> {
+    let json_path = sdk_path.join("SDKSettings.json");
+    if let Ok(contents) = std::fs::read_to_string(&json_path) {
+        if let Ok(settings) = serde_json::from_str(&contents) {
+            return Some(settings);
+        }
+    }
+
+    let plist_path = sdk_path.join("SDKSettings.plist");
+    plist::from_file(&plist_path).ok()
+}
+
+/// Build an `Sdk` for one discovered `*.sdk` directory, reading its
+/// `SDKSettings.*` for version/name/deployment-target info.
+fn discover_sdk(platform_path: &Path, sdk_path: &Path) -> Option<Sdk> {
+    let platform_name = platform_path.file_stem()?.to_str()?;
+    let target_triple = platform_target_triple(platform_name)?;
+    let settings = read_sdk_settings(sdk_path).unwrap_or_default();
+
+    // `CanonicalName` is version-embedded (e.g. `macosx14.2`), but `name` must
+    // stay a bare family so `select_sdk`'s version-stripped `--sdk` matching
+    // (see `split_sdk_name`) can find it; keep the version in `version` only.
+    let canonical_name = settings
+        .canonical_name
+        .unwrap_or_else(|| platform_name.to_lowercase());
+    let (family, embedded_version) = split_sdk_name(&canonical_name);
+    let family = family.to_string();
+    let version = settings
+        .version
+        .or_else(|| embedded_version.map(str::to_string))
+        .unwrap_or_default();
+
+    Some(Sdk {
+        name: family,
+        path: sdk_path.to_string_lossy().into_owned(),
+        version,
+        target_triple: target_triple.to_string(),
+        macosx_deployment_target: settings
+            .default_properties
+            .macosx_deployment_target
+            .unwrap_or_default(),
+        ios_deployment_target: settings
+            .default_properties
+            .ios_deployment_target
+            .unwrap_or_default(),
+        default: false,
+        toolchains: Vec::new(),
+    })
+}
+
+/// Scan `roots` for `Platforms/*.platform/Developer/SDKs/*.sdk` and build an
+/// `Sdk` for each one found, the way clang's `--infer-sdkroot-from-xcrun`
+/// discovers SDKs without hand-authored config.
+fn discover_sdks(roots: &[PathBuf]) -> Vec<Sdk> {
+    let mut discovered = Vec::new();
+
+    for root in roots {
+        let Ok(platform_entries) = std::fs::read_dir(root.join("Platforms")) else {
+            continue;
+        };
+
+        for platform_entry in platform_entries.flatten() {
+            let platform_path = platform_entry.path();
+            if platform_path.extension().and_then(|ext| ext.to_str()) != Some("platform") {
+                continue;
+            }
+
+            let Ok(sdk_entries) = std::fs::read_dir(platform_path.join("Developer/SDKs")) else {
+                continue;
+            };
+
+            for sdk_entry in sdk_entries.flatten() {
+                let sdk_path = sdk_entry.path();
+                if sdk_path.extension().and_then(|ext| ext.to_str()) != Some("sdk") {
+                    continue;
+                }
+
+                if let Some(sdk) = discover_sdk(&platform_path, &sdk_path) {
+                    discovered.push(sdk);
+                }
+            }
+        }
+    }
+
+    // `read_dir` order is unspecified, which would otherwise make
+    // `select_sdk`'s `.first()` default fallback nondeterministic across
+    // runs; sort by family, then by descending version within a family.
+    discovered.sort_by(|a, b| {
+        a.name
+            .cmp(&b.name)
+            .then_with(|| compare_versions(&b.version, &a.version))
+    });
+
+    discovered
+}
+
+/// Merge discovered SDKs with explicitly configured ones: a configured SDK
+/// overrides a discovered SDK of the same name and version, and otherwise both
+/// sets are kept.
+fn merge_sdks(discovered: Vec<Sdk>, configured: Vec<Sdk>) -> Vec<Sdk> {
+    let mut merged = discovered;
+
+    for configured_sdk in configured {
+        match merged
+            .iter_mut()
+            .find(|sdk| sdk.name == configured_sdk.name && sdk.version == configured_sdk.version)
+        {
+            Some(existing) => *existing = configured_sdk,
+            None => merged.push(configured_sdk),
+        }
+    }
+
+    merged
+}
+
+/// Platform markers that show up in Xcode-style SDK paths, e.g.
+/// `.../Platforms/iPhoneSimulator.platform/Developer/SDKs/...`.
+const PLATFORM_MARKERS: &[&str] = &[
+    "MacOSX.platform",
+    "iPhoneOS.platform",
+    "iPhoneSimulator.platform",
+    "AppleTVOS.platform",
+    "AppleTVSimulator.platform",
+    "WatchOS.platform",
+    "WatchSimulator.platform",
+];
+
+/// Determine which `PLATFORM_MARKERS` entry a given SDK belongs to, based on
+/// its `name`/`target_triple`, so an inherited `SDKROOT` can be checked for
+/// being for the wrong platform.
+fn sdk_platform_marker(sdk: &Sdk) -> Option<&'static str> {
+    let haystack = format!("{} {}", sdk.name, sdk.target_triple).to_lowercase();
+
+    if haystack.contains("iphonesimulator") {
+        Some("iPhoneSimulator.platform")
+    } else if haystack.contains("iphoneos") {
+        Some("iPhoneOS.platform")
+    } else if haystack.contains("appletvsimulator") {
+        Some("AppleTVSimulator.platform")
+    } else if haystack.contains("appletvos") {
+        Some("AppleTVOS.platform")
+    } else if haystack.contains("watchsimulator") {
+        Some("WatchSimulator.platform")
+    } else if haystack.contains("watchos") {
+        Some("WatchOS.platform")
+    } else if haystack.contains("macosx") {
+        Some("MacOSX.platform")
+    } else {
+        None
+    }
+}
+
+/// Resolve the `SDKROOT` to export to a spawned tool: honor an inherited
+/// `SDKROOT` from the environment (Clang/rustc behavior), but only if it
+/// looks like a real, plausible path for the selected SDK's platform.
+/// Falls back to the selected SDK's own path otherwise.
+fn resolve_sdkroot(sdk: &Sdk) -> String {
+    if let Ok(inherited) = std::env::var("SDKROOT") {
+        let path = PathBuf::from(&inherited);
+        let looks_bogus = !path.is_absolute() || inherited == "/" || !path.exists();
+        let wrong_platform = sdk_platform_marker(sdk).is_some_and(|expected| {
+            PLATFORM_MARKERS
+                .iter()
+                .any(|marker| *marker != expected && inherited.contains(marker))
+        });
+
+        if !looks_bogus && !wrong_platform {
+            return inherited;
+        }
+    }
+
+    sdk.path.clone()
+}
+
+/// Split a requested `--sdk` value such as `macosx10.15` into its family
+/// name (`macosx`) and an optional trailing version (`10.15`), the way real
+/// `xcrun` accepts both bare and version-pinned SDK names.
+fn split_sdk_name(requested: &str) -> (&str, Option<&str>) {
+    match requested.find(|c: char| c.is_ascii_digit()) {
+        Some(i) => (&requested[..i], Some(&requested[i..])),
+        None => (requested, None),
+    }
+}
+
+/// Compare two dotted version strings (e.g. `"10.15"` vs `"9.3"`)
+/// numerically, component by component.
+fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let parse = |v: &str| -> Vec<u64> { v.split('.').map(|c| c.parse().unwrap_or(0)).collect() };
+    parse(a).cmp(&parse(b))
+}
+
+/// Resolve the requested `--sdk` value against the SDKs in `family`: an
+/// exact version match wins, a bare family name selects the highest
+/// available version, and a requested version with no exact match picks the
+/// lowest configured version that is still `>=` the request.
+fn select_sdk_version<'a>(family: &'a [&'a Sdk], version: Option<&str>) -> Option<&'a Sdk> {
+    match version {
+        None => family
+            .iter()
+            .max_by(|a, b| compare_versions(&a.version, &b.version))
+            .copied(),
+        Some(version) => family
+            .iter()
+            .find(|sdk| sdk.version == version)
+            .copied()
+            .or_else(|| {
+                family
+                    .iter()
+                    .filter(|sdk| compare_versions(&sdk.version, version) != std::cmp::Ordering::Less)
+                    .min_by(|a, b| compare_versions(&a.version, &b.version))
+                    .copied()
+            }),
+    }
+}
+
+/// The deployment-target environment variable and value to export for
+/// `sdk`, driven off its platform. Returns `None` for platforms that don't
+/// carry a deployment target in `Sdk` yet (tvOS/watchOS/visionOS).
+fn deployment_target_env(sdk: &Sdk) -> Option<(&'static str, &str)> {
+    let (var, value) = match sdk_platform_marker(sdk) {
+        Some("iPhoneOS.platform") | Some("iPhoneSimulator.platform") => {
+            ("IPHONEOS_DEPLOYMENT_TARGET", sdk.ios_deployment_target.as_str())
+        }
+        Some("MacOSX.platform") => (
+            "MACOSX_DEPLOYMENT_TARGET",
+            sdk.macosx_deployment_target.as_str(),
+        ),
+        _ => return None,
+    };
+
+    // An empty deployment target (common for discovered SDKs whose
+    // SDKSettings didn't carry one) is worse than leaving the var unset.
+    if value.is_empty() {
+        return None;
+    }
+
+    Some((var, value))
+}
+
+/// Resolve exactly one `Sdk` out of `configuration`, the way real `xcrun`
+/// does: an explicit `--sdk <name>` wins, otherwise the SDK flagged
+/// `default = true` in the config, otherwise the first configured SDK.
+/// `--sdk` accepts both bare family names (`macosx`) and version-pinned
+/// names (`macosx10.15`); see `select_sdk_version`.
+fn select_sdk<'a>(configuration: &'a XcrunConfiguration, xcrun: &Xcrun) -> &'a Sdk {
+    if let Some(sdk_name) = &xcrun.sdk {
+        let (family, version) = split_sdk_name(sdk_name);
+        let candidates: Vec<&Sdk> = configuration
+            .sdks
+            .iter()
+            .filter(|sdk| sdk.name == family)
+            .collect();
+
+        return select_sdk_version(&candidates, version).unwrap_or_else(|| {
+            let available: Vec<&str> = candidates.iter().map(|sdk| sdk.version.as_str()).collect();
+            eprintln!(
+                "xcrun: error: unable to find SDK named {:?} (available versions for \"{}\": {})",
+                sdk_name,
+                family,
+                if available.is_empty() {
+                    "none configured".to_string()
+                } else {
+                    available.join(", ")
+                }
+            );
+            std::process::exit(1);
+        });
+    }
+
+    configuration
+        .sdks
+        .iter()
+        .find(|sdk| sdk.default)
+        .or_else(|| configuration.sdks.first())
+        .unwrap_or_else(|| {
+            eprintln!("xcrun: error: no SDKs configured");
+            std::process::exit(1);
+        })
+}
+
+/// Resolve `--toolchain <name>` against `sdk`'s configured toolchains.
+/// Returns `None` when `--toolchain` wasn't given; exits with an error when
+/// it was given but doesn't match any toolchain on `sdk`.
+fn select_toolchain<'a>(sdk: &'a Sdk, xcrun: &Xcrun) -> Option<&'a Toolchain> {
+    let name = xcrun.toolchain.as_ref()?;
+
+    Some(
+        sdk.toolchains
+            .iter()
+            .find(|toolchain| &toolchain.name == name)
+            .unwrap_or_else(|| {
+                eprintln!(
+                    "xcrun: error: unable to find toolchain named {:?} for SDK {:?}",
+                    name, sdk.name
+                );
+                std::process::exit(1);
+            }),
+    )
 }
 
 fn main() {
@@ -70,74 +447,89 @@ fn main() {
     });
     let configuration_path = PathBuf::from(xdg_config_home).join("xcrun/config.toml");
 
-    if !configuration_path.exists() {
+    let configured_sdks: Vec<Sdk> = if configuration_path.exists() {
+        let configuration: XcrunConfiguration =
+            toml::from_str(&std::fs::read_to_string(&configuration_path).unwrap()).unwrap();
+        configuration.sdks
+    } else {
+        Vec::new()
+    };
+
+    let xcrun = Xcrun::parse();
+
+    let discovered_sdks = discover_sdks(&discovery_roots(&xcrun));
+    let configuration = XcrunConfiguration {
+        sdks: merge_sdks(discovered_sdks, configured_sdks),
+    };
+
+    if configuration.sdks.is_empty() {
         eprintln!(
-            "SDK configuration file not found at {:?}",
+            "xcrun: error: no SDKs configured at {:?} and none discovered",
             configuration_path
         );
         std::process::exit(1);
     }
 
-    let configuration: XcrunConfiguration =
-        toml::from_str(&std::fs::read_to_string(configuration_path).unwrap()).unwrap();
-    let xcrun = Xcrun::parse();
+    if xcrun.kill_cache {
+        let _ = std::fs::remove_file(cache_path());
+    }
+
+    let mut cache = if xcrun.no_cache {
+        ToolCache::default()
+    } else {
+        load_cache()
+    };
+    let mut cache_dirty = false;
 
     if xcrun.version {
         println!("xcrun 1.0.0");
     }
 
     if xcrun.show_sdk_path {
-        for sdk in &configuration.sdks {
-            println!("{}", sdk.path);
-        }
+        let sdk = select_sdk(&configuration, &xcrun);
+        println!("{}", sdk.path);
     }
 
     if xcrun.show_sdk_version {
-        for sdk in &configuration.sdks {
-            println!("{}", sdk.version);
-        }
+        let sdk = select_sdk(&configuration, &xcrun);
+        println!("{}", sdk.version);
     }
 
     if xcrun.show_sdk_target_triple {
-        for sdk in &configuration.sdks {
-            println!("{}", sdk.target_triple);
-        }
+        let sdk = select_sdk(&configuration, &xcrun);
+        println!("{}", sdk.target_triple);
     }
 
     if xcrun.show_sdk_toolchain_path {
-        for sdk in &configuration.sdks {
-            println!("{}", sdk.path);
+        let sdk = select_sdk(&configuration, &xcrun);
+        match select_toolchain(sdk, &xcrun) {
+            Some(toolchain) => println!("{}", toolchain.path),
+            None => println!("{}", sdk.path),
         }
     }
 
     if xcrun.show_sdk_toolchain_version {
-        for sdk in &configuration.sdks {
-            println!("{}", sdk.version);
+        let sdk = select_sdk(&configuration, &xcrun);
+        match select_toolchain(sdk, &xcrun) {
+            Some(toolchain) => println!("{}", toolchain.version),
+            None => println!("{}", sdk.version),
         }
     }
 
     if let Some(tool) = &xcrun.find {
-        let sdk = configuration
-            .sdks
-            .iter()
-            .find(|sdk| {
-                if let Some(sdk_name) = &xcrun.sdk {
-                    sdk_name == &sdk.name
-                } else {
-                    PathBuf::from(&sdk.path).join("bin").join(&tool).exists()
-                        || PathBuf::from(&sdk.path)
-                            .join("usr/bin")
-                            .join(&tool)
-                            .exists()
-                }
-            })
-            .unwrap_or_else(|| {
-                eprintln!("xcrun: error: tool not found: {}", tool);
-                std::process::exit(1);
-            });
+        let sdk = select_sdk(&configuration, &xcrun);
+        let toolchain = select_toolchain(sdk, &xcrun);
 
-        // Locate the tool in the SDK.
-        let tool_path = find_tool(&sdk, tool).unwrap_or_else(|| {
+        // Locate the tool in the SDK (or its selected toolchain).
+        let tool_path = find_tool_cached(
+            &mut cache,
+            &mut cache_dirty,
+            xcrun.no_cache,
+            sdk,
+            toolchain,
+            tool,
+        )
+        .unwrap_or_else(|| {
             eprintln!("xcrun: error: tool not found: {}", tool);
             std::process::exit(1);
         });
@@ -147,23 +539,22 @@ fn main() {
 
     if !xcrun.arguments.is_empty() {
         let tool = xcrun.arguments[0].clone();
-        let sdk = configuration
-            .sdks
-            .iter()
-            .find(|sdk| {
-                if let Some(sdk_name) = &xcrun.sdk {
-                    sdk_name == &sdk.name
-                } else {
-                    PathBuf::from(&sdk.path).join("bin").join(&tool).exists()
-                        || PathBuf::from(&sdk.path)
-                            .join("usr/bin")
-                            .join(&tool)
-                            .exists()
-                }
-            })
-            .unwrap();
-        let mut command = Command::new(find_tool(&sdk, &tool).unwrap());
-        command.env("SDKROOT", sdk.path.clone());
+        let sdk = select_sdk(&configuration, &xcrun);
+        let toolchain = select_toolchain(sdk, &xcrun);
+        let tool_path = find_tool_cached(
+            &mut cache,
+            &mut cache_dirty,
+            xcrun.no_cache,
+            sdk,
+            toolchain,
+            &tool,
+        )
+        .unwrap_or_else(|| {
+            eprintln!("xcrun: error: tool not found: {}", tool);
+            std::process::exit(1);
+        });
+        let mut command = Command::new(tool_path);
+        command.env("SDKROOT", resolve_sdkroot(sdk));
         command.env(
             "PATH",
             format!("{}:{}", sdk.path, std::env::var("PATH").unwrap()),
@@ -176,6 +567,11 @@ fn main() {
                 std::env::var("LD_LIBRARY_PATH").unwrap_or_default()
             ),
         );
+        if let Some((var, value)) = deployment_target_env(sdk) {
+            if std::env::var_os(var).is_none() {
+                command.env(var, value);
+            }
+        }
         command.args(&xcrun.arguments[1..]);
         command.stdin(std::process::Stdio::inherit());
         command.stderr(std::process::Stdio::inherit());
@@ -189,15 +585,31 @@ fn main() {
         }
 
         let status = command.status().unwrap();
+        if cache_dirty {
+            save_cache(&cache);
+        }
         std::process::exit(status.code().unwrap());
     }
 
     if xcrun.verbose {
         println!("{:?}", xcrun);
     }
+
+    if cache_dirty {
+        save_cache(&cache);
+    }
 }
 
-fn find_tool(sdk: &Sdk, tool: &str) -> Option<PathBuf> {
+/// Locate `tool`, searching the selected `toolchain`'s `usr/bin` ahead of
+/// `sdk`'s own `bin`/`usr/bin`.
+fn find_tool(sdk: &Sdk, toolchain: Option<&Toolchain>, tool: &str) -> Option<PathBuf> {
+    if let Some(toolchain) = toolchain {
+        let tool_path = PathBuf::from(&toolchain.path).join("usr/bin").join(tool);
+        if tool_path.exists() {
+            return Some(tool_path);
+        }
+    }
+
     let tool_path = PathBuf::from(&sdk.path).join("bin").join(tool);
     if tool_path.exists() {
         return Some(tool_path);
@@ -210,3 +622,84 @@ fn find_tool(sdk: &Sdk, tool: &str) -> Option<PathBuf> {
 
     None
 }
+
+/// On-disk cache of resolved `(sdk_name, sdk_path, toolchain_name, tool_name) -> path`
+/// lookups (see `cache_key`), persisted under `$XDG_CACHE_HOME/xcrun/cache.toml`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ToolCache {
+    #[serde(default)]
+    tools: HashMap<String, String>,
+}
+
+fn cache_path() -> PathBuf {
+    let xdg_cache_home = std::env::var("XDG_CACHE_HOME").unwrap_or_else(|_| {
+        let home = std::env::var("HOME").unwrap();
+        format!("{}/.cache", home)
+    });
+    PathBuf::from(xdg_cache_home).join("xcrun/cache.toml")
+}
+
+fn load_cache() -> ToolCache {
+    std::fs::read_to_string(cache_path())
+        .ok()
+        .and_then(|contents| toml::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_cache(cache: &ToolCache) {
+    let path = cache_path();
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    if let Ok(contents) = toml::to_string_pretty(cache) {
+        let _ = std::fs::write(path, contents);
+    }
+}
+
+fn cache_key(sdk: &Sdk, toolchain: Option<&Toolchain>, tool: &str) -> String {
+    // `sdk.name` is only the bare family (e.g. "macosx"); multiple SDKs of
+    // the same family can coexist, so the key must pin the exact SDK path,
+    // not just its family, or a lookup can resolve to the wrong sysroot.
+    format!(
+        "{}:{}:{}:{}",
+        sdk.name,
+        sdk.path,
+        toolchain.map(|toolchain| toolchain.name.as_str()).unwrap_or(""),
+        tool
+    )
+}
+
+/// Resolve `tool` within `sdk`/`toolchain`, consulting `cache` first (and
+/// validating the cached path still exists) unless `no_cache` is set.
+/// Newly-resolved paths are written back into `cache`, with `dirty` flagged
+/// so the caller knows to persist it.
+fn find_tool_cached(
+    cache: &mut ToolCache,
+    dirty: &mut bool,
+    no_cache: bool,
+    sdk: &Sdk,
+    toolchain: Option<&Toolchain>,
+    tool: &str,
+) -> Option<PathBuf> {
+    let key = cache_key(sdk, toolchain, tool);
+
+    if !no_cache {
+        if let Some(cached_path) = cache.tools.get(&key) {
+            let cached_path = PathBuf::from(cached_path);
+            if cached_path.exists() {
+                return Some(cached_path);
+            }
+        }
+    }
+
+    let tool_path = find_tool(sdk, toolchain, tool)?;
+
+    if !no_cache {
+        cache
+            .tools
+            .insert(key, tool_path.display().to_string());
+        *dirty = true;
+    }
+
+    Some(tool_path)
+}